@@ -0,0 +1,113 @@
+//! Base64 helpers for embedding VCDIFF patches in text-only transports.
+//!
+//! Patches produced by [`crate::encode`] are raw binary, which doesn't
+//! survive unmodified in JSON, URLs, or email bodies. These wrappers base64
+//! encode/decode a patch around the existing [`crate::encode`]/
+//! [`crate::decode`] calls, with the alphabet selectable via
+//! [`Base64Alphabet`].
+
+use crate::{decode, encode, Error};
+use base64ct::Encoding;
+
+/// Which base64 alphabet to use, mirroring `base64ct`'s `Base64`/
+/// `Base64Url`/`Base64UrlUnpadded` encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Base64Alphabet {
+    /// The standard alphabet (`+`/`/`), padded with `=`.
+    #[default]
+    Standard,
+    /// The URL- and filename-safe alphabet (`-`/`_`), padded with `=`.
+    UrlSafe,
+    /// The URL- and filename-safe alphabet (`-`/`_`), unpadded.
+    UrlSafeUnpadded,
+}
+
+/// Diffs `input` against `src` and base64-encodes the resulting patch using
+/// the standard alphabet. See [`encode_base64_with_alphabet`] to select a
+/// different alphabet.
+///
+/// ```
+/// extern crate xdelta3;
+/// use xdelta3::base64::{decode_base64, encode_base64};
+///
+/// fn main() {
+///     let input = [1, 2, 3, 4, 5, 6, 7];
+///     let src = [1, 2, 4, 4, 7, 6, 7];
+///
+///     let patch = encode_base64(&input, &src).unwrap();
+///     let decoded = decode_base64(&patch, &src).unwrap();
+///     assert_eq!(decoded, input);
+/// }
+/// ```
+pub fn encode_base64(input: &[u8], src: &[u8]) -> Result<String, Error> {
+    encode_base64_with_alphabet(input, src, Base64Alphabet::Standard)
+}
+
+/// Like [`encode_base64`], but with an explicit [`Base64Alphabet`].
+pub fn encode_base64_with_alphabet(
+    input: &[u8],
+    src: &[u8],
+    alphabet: Base64Alphabet,
+) -> Result<String, Error> {
+    let patch = encode(input, src)?;
+    Ok(match alphabet {
+        Base64Alphabet::Standard => base64ct::Base64::encode_string(&patch),
+        Base64Alphabet::UrlSafe => base64ct::Base64Url::encode_string(&patch),
+        Base64Alphabet::UrlSafeUnpadded => base64ct::Base64UrlUnpadded::encode_string(&patch),
+    })
+}
+
+/// Decodes a base64 patch produced by [`encode_base64`] and applies it to
+/// `src`. Malformed base64 is reported as [`Error::InvalidEncoding`] rather
+/// than panicking.
+pub fn decode_base64(patch: &str, src: &[u8]) -> Result<Vec<u8>, Error> {
+    decode_base64_with_alphabet(patch, src, Base64Alphabet::Standard)
+}
+
+/// Like [`decode_base64`], but with an explicit [`Base64Alphabet`].
+pub fn decode_base64_with_alphabet(
+    patch: &str,
+    src: &[u8],
+    alphabet: Base64Alphabet,
+) -> Result<Vec<u8>, Error> {
+    // Email and some URL transports wrap or pad lines with whitespace;
+    // base64ct's decoder is strict RFC4648 and rejects it outright.
+    let patch: String = patch.chars().filter(|c| !c.is_ascii_whitespace()).collect();
+    let bytes = match alphabet {
+        Base64Alphabet::Standard => base64ct::Base64::decode_vec(&patch),
+        Base64Alphabet::UrlSafe => base64ct::Base64Url::decode_vec(&patch),
+        Base64Alphabet::UrlSafeUnpadded => base64ct::Base64UrlUnpadded::decode_vec(&patch),
+    }
+    .map_err(|_| Error::InvalidEncoding)?;
+    decode(&bytes, src)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: [u8; 7] = [1, 2, 4, 4, 7, 6, 7];
+
+    #[test]
+    fn malformed_base64_is_reported_as_invalid_encoding_not_a_panic() {
+        assert!(matches!(
+            decode_base64("not valid base64!!!", &SRC),
+            Err(Error::InvalidEncoding)
+        ));
+    }
+
+    #[test]
+    fn whitespace_wrapped_base64_is_tolerated() {
+        let input = [1, 2, 3, 4, 5, 6, 7];
+        let patch = encode_base64(&input, &SRC).unwrap();
+
+        let wrapped: String = patch
+            .as_bytes()
+            .chunks(4)
+            .map(|chunk| std::str::from_utf8(chunk).unwrap())
+            .collect::<Vec<_>>()
+            .join("\r\n");
+
+        assert_eq!(decode_base64(&wrapped, &SRC).unwrap(), input);
+    }
+}
@@ -0,0 +1,198 @@
+//! Self-describing VCDIFF patch container.
+//!
+//! A raw VCDIFF patch produced by [`crate::encode`] carries no information
+//! about how large the decoded output is or which `src` it was built
+//! against, so [`crate::decode`] forces callers to guess an output buffer
+//! size (surfacing [`Error::InsufficientOutputLength`](crate::Error) on a
+//! too-small guess) and has no way to detect a mismatched `src` until the
+//! decoded bytes come out wrong. This module wraps a VCDIFF patch in a small
+//! header: a magic prefix, a version byte, the exact decoded length encoded
+//! as LEB128, the CRC32 of `src`, and the CRC32 of the expected decoded
+//! output.
+
+use crate::{decode_with_output_len, encode_with_config, EncodeConfig, Error};
+
+/// Identifies a container-wrapped patch produced by this crate.
+const MAGIC: [u8; 4] = *b"XD3C";
+
+/// Container format version, bumped if the header layout changes.
+const VERSION: u8 = 1;
+
+fn write_leb128(buf: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let mut byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        buf.push(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Maximum number of bytes in a LEB128 encoding of a `u64` (`ceil(64 / 7)`).
+const LEB128_MAX_BYTES: usize = 10;
+
+fn read_leb128(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    for _ in 0..LEB128_MAX_BYTES {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+    None
+}
+
+/// Diffs `input` against `src` with `config`, wrapping the resulting VCDIFF
+/// patch in a self-describing container that [`decode_container`] can parse
+/// without guessing the output length or the source.
+///
+/// ```
+/// extern crate xdelta3;
+/// use xdelta3::container::{decode_container, encode_container};
+/// use xdelta3::EncodeConfig;
+///
+/// fn main() {
+///     let input = [1, 2, 3, 4, 5, 6, 7];
+///     let src = [1, 2, 4, 4, 7, 6, 7];
+///
+///     let patch = encode_container(&input, &src, &EncodeConfig::default()).unwrap();
+///     let decoded = decode_container(&patch, &src).unwrap();
+///     assert_eq!(decoded, input);
+/// }
+/// ```
+pub fn encode_container(
+    input: &[u8],
+    src: &[u8],
+    config: &EncodeConfig,
+) -> Result<Vec<u8>, Error> {
+    let patch = encode_with_config(input, src, config)?;
+
+    let mut out = Vec::with_capacity(MAGIC.len() + 1 + 10 + 4 + 4 + patch.len());
+    out.extend_from_slice(&MAGIC);
+    out.push(VERSION);
+    write_leb128(&mut out, input.len() as u64);
+    out.extend_from_slice(&crc32fast::hash(src).to_le_bytes());
+    out.extend_from_slice(&crc32fast::hash(input).to_le_bytes());
+    out.extend_from_slice(&patch);
+    Ok(out)
+}
+
+/// Parses a container produced by [`encode_container`], verifies `src`'s
+/// CRC32 matches before doing any decode work, decodes using the stored
+/// exact output length (no guessing), then verifies the decoded output's
+/// CRC32 before returning it.
+pub fn decode_container(patch: &[u8], src: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+
+    if patch.get(..MAGIC.len()) != Some(&MAGIC[..]) {
+        return Err(Error::MagicMismatch);
+    }
+    pos += MAGIC.len();
+
+    if patch.get(pos) != Some(&VERSION) {
+        return Err(Error::MagicMismatch);
+    }
+    pos += 1;
+
+    let output_len = read_leb128(patch, &mut pos).ok_or(Error::MagicMismatch)?;
+
+    let src_crc = patch
+        .get(pos..pos + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(Error::MagicMismatch)?;
+    pos += 4;
+
+    let output_crc = patch
+        .get(pos..pos + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or(Error::MagicMismatch)?;
+    pos += 4;
+
+    if crc32fast::hash(src) != src_crc {
+        return Err(Error::SourceChecksumMismatch);
+    }
+
+    let vcdiff = &patch[pos..];
+    let output = decode_with_output_len(vcdiff, src, output_len as u32)?;
+
+    if crc32fast::hash(&output) != output_crc {
+        return Err(Error::OutputChecksumMismatch);
+    }
+
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: [u8; 7] = [1, 2, 3, 4, 5, 6, 7];
+    const SRC: [u8; 7] = [1, 2, 4, 4, 7, 6, 7];
+
+    #[test]
+    fn malformed_length_with_continuation_bit_always_set_is_rejected() {
+        // 11 bytes with the high bit set is one more than LEB128_MAX_BYTES
+        // can represent for a u64; prior to the fix this overflowed the
+        // shift and panicked instead of returning an error.
+        let mut patch = Vec::new();
+        patch.extend_from_slice(&MAGIC);
+        patch.push(VERSION);
+        patch.extend(std::iter::repeat(0x80u8).take(11));
+
+        assert!(matches!(
+            decode_container(&patch, &SRC),
+            Err(Error::MagicMismatch)
+        ));
+    }
+
+    #[test]
+    fn magic_mismatch_is_rejected() {
+        let mut patch = encode_container(&INPUT, &SRC, &EncodeConfig::default()).unwrap();
+        patch[0] ^= 0xff;
+
+        assert!(matches!(
+            decode_container(&patch, &SRC),
+            Err(Error::MagicMismatch)
+        ));
+    }
+
+    #[test]
+    fn source_checksum_mismatch_is_rejected() {
+        let patch = encode_container(&INPUT, &SRC, &EncodeConfig::default()).unwrap();
+        let wrong_src = [9, 9, 9, 9, 9, 9, 9];
+
+        assert!(matches!(
+            decode_container(&patch, &wrong_src),
+            Err(Error::SourceChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn output_checksum_mismatch_is_rejected() {
+        let mut patch = encode_container(&INPUT, &SRC, &EncodeConfig::default()).unwrap();
+        // The output CRC32 is the 4 bytes right after the source CRC32,
+        // which sits right after the magic, version, and LEB128 length.
+        let output_crc_pos = MAGIC.len() + 1 + 1 + 4;
+        patch[output_crc_pos] ^= 0xff;
+
+        assert!(matches!(
+            decode_container(&patch, &SRC),
+            Err(Error::OutputChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn round_trip() {
+        let patch = encode_container(&INPUT, &SRC, &EncodeConfig::default()).unwrap();
+        let decoded = decode_container(&patch, &SRC).unwrap();
+        assert_eq!(decoded, INPUT);
+    }
+}
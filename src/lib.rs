@@ -16,6 +16,11 @@ use std::ffi::CStr;
 #[cfg(feature = "stream")]
 pub mod stream;
 
+#[cfg(feature = "container")]
+pub mod container;
+#[cfg(feature = "base64")]
+pub mod base64;
+
 #[allow(dead_code)]
 mod binding {
     #![allow(non_upper_case_globals)]
@@ -34,6 +39,20 @@ pub enum Error {
         expected_length: u32,
         actual_length: u32,
     },
+    Io(std::io::Error),
+    /// A [`container`](crate::container)-wrapped patch is missing or has a
+    /// mismatched magic prefix/version, or its header could not be parsed.
+    MagicMismatch,
+    /// The `src` passed to [`container::decode_container`] does not match
+    /// the CRC32 recorded when the patch was created.
+    SourceChecksumMismatch,
+    /// The decoded output does not match the CRC32 recorded when the patch
+    /// was created, meaning decoding produced unexpected data.
+    OutputChecksumMismatch,
+    /// A string passed to [`base64::decode_base64`] (or one of its
+    /// alphabet-specific variants) is not valid base64 for the selected
+    /// alphabet.
+    InvalidEncoding,
 }
 
 impl std::fmt::Debug for Error {
@@ -52,10 +71,21 @@ impl std::fmt::Debug for Error {
             } => {
                 write!(f, "OutOfBounds: {} > {}", actual_length, expected_length)
             }
+            Self::Io(err) => write!(f, "Io: {}", err),
+            Self::MagicMismatch => write!(f, "MagicMismatch"),
+            Self::SourceChecksumMismatch => write!(f, "SourceChecksumMismatch"),
+            Self::OutputChecksumMismatch => write!(f, "OutputChecksumMismatch"),
+            Self::InvalidEncoding => write!(f, "InvalidEncoding"),
         }
     }
 }
 
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
 /// Function to generate the difference data
 ///
 /// This function is used to generate the difference data.
@@ -84,7 +114,161 @@ impl std::fmt::Debug for Error {
 /// But don't worry, if your data is large enough and kind of similar between each other (usually the case
 /// for software updates or ROM patches), the patch data should be only a fraction of your updated file.
 pub fn encode(input: &[u8], src: &[u8]) -> Result<Vec<u8>, Error> {
-    encode_with_output_len(input, src, (input.len() + src.len()) as u32 * 2)
+    encode_with_config(input, src, &EncodeConfig::default())
+}
+
+/// Secondary compressor applied to a VCDIFF window's literal, address, and
+/// instruction sections, corresponding to the `XD3_SEC_*` flags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryCompression {
+    /// DJW static Huffman compression.
+    Djw,
+    /// FGK adaptive Huffman compression.
+    Fgk,
+    /// LZMA compression. Only available when the underlying C library was
+    /// built with LZMA support; otherwise `xdelta3` reports an error.
+    Lzma,
+}
+
+impl SecondaryCompression {
+    fn flag(self) -> c_uint {
+        match self {
+            Self::Djw => binding::XD3_SEC_DJW,
+            Self::Fgk => binding::XD3_SEC_FGK,
+            Self::Lzma => binding::XD3_SEC_LZMA,
+        }
+    }
+}
+
+/// Configuration for [`encode_with_config`].
+///
+/// By default no secondary compressor is applied and `xdelta3` runs at its
+/// default compression effort. Use [`secondary_compression`](Self::secondary_compression)
+/// and [`compression_level`](Self::compression_level) to trade CPU time for
+/// smaller patches.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodeConfig {
+    secondary_compression: Option<SecondaryCompression>,
+    compression_level: Option<u8>,
+}
+
+impl EncodeConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Selects a secondary compressor to further shrink the VCDIFF output.
+    pub fn secondary_compression(mut self, compression: SecondaryCompression) -> Self {
+        self.secondary_compression = Some(compression);
+        self
+    }
+
+    /// Sets the compression level, from `1` (fastest) to `9` (smallest output).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is not in `1..=9`.
+    pub fn compression_level(mut self, level: u8) -> Self {
+        assert!(
+            (1..=9).contains(&level),
+            "compression level must be in 1..=9, got {}",
+            level
+        );
+        self.compression_level = Some(level);
+        self
+    }
+
+    fn flags(&self) -> c_uint {
+        let mut flags = 0;
+        if let Some(compression) = self.secondary_compression {
+            flags |= compression.flag();
+        }
+        if let Some(level) = self.compression_level {
+            let complevel = match level {
+                1 => binding::XD3_COMPLEVEL_1,
+                2 => binding::XD3_COMPLEVEL_2,
+                3 => binding::XD3_COMPLEVEL_3,
+                4 => binding::XD3_COMPLEVEL_4,
+                5 => binding::XD3_COMPLEVEL_5,
+                6 => binding::XD3_COMPLEVEL_6,
+                7 => binding::XD3_COMPLEVEL_7,
+                8 => binding::XD3_COMPLEVEL_8,
+                9 => binding::XD3_COMPLEVEL_9,
+                _ => unreachable!("compression_level validated to be in 1..=9"),
+            };
+            flags |= complevel << binding::XD3_COMPLEVEL_SHIFT;
+        }
+        flags
+    }
+}
+
+/// Like [`encode`], but with a [`EncodeConfig`] selecting secondary
+/// compression and/or a compression level.
+///
+/// This is the primary encoding entry point; `encode` simply calls this with
+/// the default (no secondary compression, default effort) config.
+///
+/// ```
+/// extern crate xdelta3;
+/// use xdelta3::{encode, encode_with_config, EncodeConfig, SecondaryCompression};
+///
+/// fn main() {
+///     let input = [1, 2, 3, 4, 5, 6, 7];
+///     let src = [1, 2, 4, 4, 7, 6, 7];
+///
+///     let config = EncodeConfig::new()
+///         .secondary_compression(SecondaryCompression::Djw)
+///         .compression_level(9);
+///     let configured = encode_with_config(&input, &src, &config).unwrap();
+///     let default = encode(&input, &src).unwrap();
+///
+///     // Enabling secondary compression changes the flags passed to
+///     // xdelta3, which changes the emitted patch bytes.
+///     assert_ne!(configured, default);
+///     // Decoding with either path recovers the original `input`.
+///     assert_eq!(xdelta3::decode(&configured, &src).unwrap(), input);
+///     assert_eq!(xdelta3::decode(&default, &src).unwrap(), input);
+/// }
+/// ```
+pub fn encode_with_config(
+    input: &[u8],
+    src: &[u8],
+    config: &EncodeConfig,
+) -> Result<Vec<u8>, Error> {
+    let output_buffer_len = (input.len() + src.len()) as u32 * 2;
+    let input_len = input.len() as c_uint;
+    let src_len = src.len() as c_uint;
+    let mut avail_output = 0 as c_uint;
+    let mut output = Vec::with_capacity(output_buffer_len as usize);
+    let error_code = unsafe {
+        binding::xd3_encode_memory(
+            input.as_ptr(),
+            input_len,
+            src.as_ptr(),
+            src_len,
+            output.as_mut_ptr(),
+            &mut avail_output,
+            output_buffer_len,
+            config.flags(),
+        )
+    };
+    if error_code == 0 {
+        // Extra sanity check to prevent UB.
+        if avail_output > output_buffer_len {
+            return Err(Error::OutOfBounds {
+                expected_length: output_buffer_len,
+                actual_length: avail_output,
+            });
+        }
+        unsafe {
+            output.set_len(avail_output as usize);
+        }
+        Ok(output)
+    } else if error_code == libc::ENOSPC {
+        Err(Error::InsufficientOutputLength)
+    } else {
+        Err(Error::XDelta3 { error_code })
+    }
 }
 
 pub fn encode_with_output_len(
@@ -193,3 +377,163 @@ pub fn decode_with_output_len(
         Err(Error::XDelta3 { error_code })
     }
 }
+
+/// Default cap on how large `encode_growable`/`decode_growable` will grow
+/// the output buffer before giving up.
+pub const DEFAULT_MAX_OUTPUT_LEN: u32 = u32::MAX;
+
+/// Like [`encode`], but instead of requiring a pre-sized output buffer, this
+/// starts at `initial_output_len` and doubles it (up to `max_output_len`)
+/// each time the underlying call reports [`Error::InsufficientOutputLength`],
+/// so callers who have no idea how large the patch will be don't need to
+/// guess or retry manually.
+///
+/// ```
+/// extern crate xdelta3;
+/// use xdelta3::{encode_growable, DEFAULT_MAX_OUTPUT_LEN};
+///
+/// fn main() {
+///     // Start from 0: no idea how big the patch will be.
+///     let result = encode_growable(&[1, 2, 3, 4, 5, 6, 7], &[1, 2, 4, 4, 7, 6, 7], 0, DEFAULT_MAX_OUTPUT_LEN);
+///     assert_eq!(result.unwrap().as_slice(), &[214, 195, 196, 0, 0, 0, 13, 7, 0, 7, 1, 0, 1, 2, 3, 4, 5, 6, 7, 8]);
+/// }
+/// ```
+pub fn encode_growable(
+    input: &[u8],
+    src: &[u8],
+    initial_output_len: u32,
+    max_output_len: u32,
+) -> Result<Vec<u8>, Error> {
+    let mut output_buffer_len = initial_output_len;
+    loop {
+        match encode_with_output_len(input, src, output_buffer_len) {
+            Err(Error::InsufficientOutputLength) if output_buffer_len < max_output_len => {
+                output_buffer_len = output_buffer_len.max(1).saturating_mul(2).min(max_output_len);
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Like [`decode`], but instead of requiring a pre-sized output buffer, this
+/// starts at `initial_output_len` and doubles it (up to `max_output_len`)
+/// each time the underlying call reports [`Error::InsufficientOutputLength`],
+/// so callers who have no idea how large the decoded data will be don't need
+/// to guess or retry manually.
+///
+/// ```
+/// extern crate xdelta3;
+/// use xdelta3::{decode_growable, DEFAULT_MAX_OUTPUT_LEN};
+///
+/// fn main() {
+///     // Start from 0: no idea how big the decoded data will be.
+///     let result = decode_growable(
+///         &[214, 195, 196, 0, 0, 0, 13, 7, 0, 7, 1, 0, 1, 2, 3, 4, 5, 6, 7, 8],
+///         &[1, 2, 4, 4, 7, 6, 7],
+///         0,
+///         DEFAULT_MAX_OUTPUT_LEN,
+///     );
+///     assert_eq!(result.unwrap().as_slice(), &[1, 2, 3, 4, 5, 6, 7]);
+/// }
+/// ```
+pub fn decode_growable(
+    input: &[u8],
+    src: &[u8],
+    initial_output_len: u32,
+    max_output_len: u32,
+) -> Result<Vec<u8>, Error> {
+    let mut output_buffer_len = initial_output_len;
+    loop {
+        match decode_with_output_len(input, src, output_buffer_len) {
+            Err(Error::InsufficientOutputLength) if output_buffer_len < max_output_len => {
+                output_buffer_len = output_buffer_len.max(1).saturating_mul(2).min(max_output_len);
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: [u8; 7] = [1, 2, 3, 4, 5, 6, 7];
+    const SRC: [u8; 7] = [1, 2, 4, 4, 7, 6, 7];
+
+    #[test]
+    fn encode_growable_from_zero_terminates_and_matches_encode() {
+        let result = encode_growable(&INPUT, &SRC, 0, DEFAULT_MAX_OUTPUT_LEN).unwrap();
+        assert_eq!(result, encode(&INPUT, &SRC).unwrap());
+    }
+
+    #[test]
+    fn decode_growable_from_zero_terminates_and_matches_decode() {
+        let patch = encode(&INPUT, &SRC).unwrap();
+        let result = decode_growable(&patch, &SRC, 0, DEFAULT_MAX_OUTPUT_LEN).unwrap();
+        assert_eq!(result, INPUT);
+    }
+
+    #[test]
+    fn encode_growable_returns_err_once_cap_is_exhausted() {
+        // initial == max == 0 leaves no room to grow, so this must return
+        // `Err` immediately rather than looping forever.
+        assert!(matches!(
+            encode_growable(&INPUT, &SRC, 0, 0),
+            Err(Error::InsufficientOutputLength)
+        ));
+    }
+
+    #[test]
+    fn decode_growable_returns_err_once_cap_is_exhausted() {
+        let patch = encode(&INPUT, &SRC).unwrap();
+        assert!(matches!(
+            decode_growable(&patch, &SRC, 0, 0),
+            Err(Error::InsufficientOutputLength)
+        ));
+    }
+
+    #[test]
+    fn default_encode_config_has_no_flags() {
+        assert_eq!(EncodeConfig::default().flags(), 0);
+    }
+
+    #[test]
+    fn secondary_compression_sets_the_matching_sec_flag() {
+        assert_eq!(
+            EncodeConfig::new()
+                .secondary_compression(SecondaryCompression::Djw)
+                .flags(),
+            binding::XD3_SEC_DJW
+        );
+        assert_eq!(
+            EncodeConfig::new()
+                .secondary_compression(SecondaryCompression::Fgk)
+                .flags(),
+            binding::XD3_SEC_FGK
+        );
+        assert_eq!(
+            EncodeConfig::new()
+                .secondary_compression(SecondaryCompression::Lzma)
+                .flags(),
+            binding::XD3_SEC_LZMA
+        );
+    }
+
+    #[test]
+    fn compression_level_is_shifted_into_the_complevel_bits() {
+        let flags = EncodeConfig::new().compression_level(9).flags();
+        assert_eq!(flags, binding::XD3_COMPLEVEL_9 << binding::XD3_COMPLEVEL_SHIFT);
+        // And it composes with a secondary compressor rather than replacing it.
+        let combined = EncodeConfig::new()
+            .secondary_compression(SecondaryCompression::Lzma)
+            .compression_level(9)
+            .flags();
+        assert_eq!(combined, binding::XD3_SEC_LZMA | flags);
+    }
+
+    #[test]
+    #[should_panic]
+    fn compression_level_out_of_range_panics() {
+        EncodeConfig::new().compression_level(0);
+    }
+}
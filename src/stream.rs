@@ -0,0 +1,241 @@
+//! Streaming encode/decode for inputs too large to hold in memory.
+//!
+//! [`crate::encode`] and [`crate::decode`] require `src` and `input` to be
+//! fully resident `&[u8]` slices, and size their output buffer as
+//! `(input.len() + src.len()) * 2`. For multi-gigabyte files that's not an
+//! option. This module instead drives xdelta3's window-based streaming API
+//! (`xd3_stream`) directly: `input` is read through a fixed-size buffer and
+//! fed to the library a block at a time, and source blocks are fetched from
+//! `src` on demand, so memory use is bounded by the buffer and window size
+//! rather than by file length.
+
+use crate::binding;
+use crate::Error;
+use libc::c_uint;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Size of the buffer used to feed `input` to the stream, in bytes.
+const BUF_SIZE: usize = 64 * 1024;
+
+/// Size of a source block fetched on demand via `XD3_GETSRCBLK`.
+const SRC_BLK_SIZE: usize = 128 * 1024;
+
+/// Caches the two most recently served source blocks.
+///
+/// A VCDIFF copy instruction isn't block-aligned, so a match can straddle a
+/// `SRC_BLK_SIZE` boundary and reference two blocks at once. A single reused
+/// buffer would clobber the earlier block's bytes the moment the next one is
+/// requested, silently corrupting output instead of erroring. Two slots are
+/// enough to keep both sides of a boundary-straddling match resident at
+/// once, while still bounding memory to a small, fixed number of blocks.
+struct BlockCache {
+    blksize: usize,
+    slots: [Option<(u64, Vec<u8>)>; 2],
+    next: usize,
+}
+
+impl BlockCache {
+    fn new(blksize: usize) -> Self {
+        Self {
+            blksize,
+            slots: [None, None],
+            next: 0,
+        }
+    }
+
+    /// Returns a pointer/length pair for `blkno`, reading it from `src` if
+    /// it isn't already cached.
+    fn fill<R: Read + Seek>(
+        &mut self,
+        src: &mut R,
+        blkno: u64,
+    ) -> std::io::Result<(*const u8, usize)> {
+        if let Some(idx) = self
+            .slots
+            .iter()
+            .position(|slot| matches!(slot, Some((b, _)) if *b == blkno))
+        {
+            self.next = (idx + 1) % self.slots.len();
+            let (_, buf) = self.slots[idx].as_ref().unwrap();
+            return Ok((buf.as_ptr(), buf.len()));
+        }
+
+        let idx = self.next;
+        self.next = (self.next + 1) % self.slots.len();
+
+        src.seek(SeekFrom::Start(blkno * self.blksize as u64))?;
+        let mut buf = vec![0u8; self.blksize];
+        let mut filled = 0;
+        while filled < buf.len() {
+            let n = src.read(&mut buf[filled..])?;
+            if n == 0 {
+                break;
+            }
+            filled += n;
+        }
+        buf.truncate(filled);
+        self.slots[idx] = Some((blkno, buf));
+
+        let (_, buf) = self.slots[idx].as_ref().unwrap();
+        Ok((buf.as_ptr(), buf.len()))
+    }
+}
+
+/// Drives `xd3_encode_input`/`xd3_decode_input` to completion, reading
+/// `input` and `src` through bounded buffers and writing the result to `out`.
+fn run<R: Read + Seek, W: Write>(
+    mut input: R,
+    mut src: R,
+    mut out: W,
+    is_encode: bool,
+) -> Result<(), Error> {
+    let mut stream: binding::xd3_stream = unsafe { std::mem::zeroed() };
+    let mut config: binding::xd3_config = unsafe { std::mem::zeroed() };
+    unsafe { binding::xd3_init_config(&mut config, 0) };
+    let error_code = unsafe { binding::xd3_config_stream(&mut stream, &mut config) };
+    if error_code != 0 {
+        return Err(Error::XDelta3 { error_code });
+    }
+
+    let mut source: binding::xd3_source = unsafe { std::mem::zeroed() };
+    source.blksize = SRC_BLK_SIZE as c_uint;
+    let mut src_blocks = BlockCache::new(SRC_BLK_SIZE);
+
+    let error_code = unsafe { binding::xd3_set_source(&mut stream, &mut source) };
+    if error_code != 0 {
+        unsafe { binding::xd3_free_stream(&mut stream) };
+        return Err(Error::XDelta3 { error_code });
+    }
+
+    let mut in_buf = vec![0u8; BUF_SIZE];
+    let mut eof = false;
+    let result = (|| -> Result<(), Error> {
+        loop {
+            if !eof && stream.avail_in == 0 {
+                let n = input.read(&mut in_buf)?;
+                if n == 0 {
+                    eof = true;
+                    stream.flags |= binding::XD3_FLUSH as i32;
+                    unsafe { binding::xd3_avail_input(&mut stream, in_buf.as_ptr(), 0) };
+                } else {
+                    unsafe { binding::xd3_avail_input(&mut stream, in_buf.as_ptr(), n as c_uint) };
+                }
+            }
+
+            let ret = unsafe {
+                if is_encode {
+                    binding::xd3_encode_input(&mut stream)
+                } else {
+                    binding::xd3_decode_input(&mut stream)
+                }
+            };
+
+            match ret as u32 {
+                binding::xd3_rvalues_XD3_INPUT => {
+                    if eof {
+                        break;
+                    }
+                }
+                binding::xd3_rvalues_XD3_OUTPUT => {
+                    let data = unsafe {
+                        std::slice::from_raw_parts(stream.next_out, stream.avail_out as usize)
+                    };
+                    out.write_all(data)?;
+                    unsafe { binding::xd3_consume_output(&mut stream) };
+                }
+                binding::xd3_rvalues_XD3_GETSRCBLK => {
+                    let (ptr, len) = src_blocks.fill(&mut src, source.getblkno as u64)?;
+                    source.curblk = ptr;
+                    source.curblkno = source.getblkno;
+                    source.onblk = len as c_uint;
+                }
+                binding::xd3_rvalues_XD3_GOTHEADER
+                | binding::xd3_rvalues_XD3_WINSTART
+                | binding::xd3_rvalues_XD3_WINFINISH => {}
+                _ => {
+                    return Err(Error::XDelta3 { error_code: ret });
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    unsafe {
+        binding::xd3_close_stream(&mut stream);
+        binding::xd3_free_stream(&mut stream);
+    }
+    result
+}
+
+/// Diffs `input` against `src`, writing the VCDIFF patch to `out`.
+///
+/// Unlike [`crate::encode`], `input` and `src` are read through bounded
+/// buffers rather than loaded up front, so this is suitable for files too
+/// large to fit in memory. `src` must support [`Seek`] so source blocks can
+/// be re-read on demand as the encoder requests them.
+///
+/// ```
+/// extern crate xdelta3;
+/// use std::io::Cursor;
+/// use xdelta3::stream::{decode_stream, encode_stream};
+///
+/// fn main() {
+///     let input = [1, 2, 3, 4, 5, 6, 7];
+///     let src = [1, 2, 4, 4, 7, 6, 7];
+///
+///     let mut patch = Vec::new();
+///     encode_stream(Cursor::new(&input[..]), Cursor::new(&src[..]), &mut patch).unwrap();
+///
+///     let mut roundtripped = Vec::new();
+///     decode_stream(Cursor::new(&patch[..]), Cursor::new(&src[..]), &mut roundtripped).unwrap();
+///     assert_eq!(roundtripped, input);
+/// }
+/// ```
+pub fn encode_stream<R: Read + Seek, W: Write>(
+    input: R,
+    src: R,
+    out: W,
+) -> Result<(), Error> {
+    run(input, src, out, true)
+}
+
+/// Applies the VCDIFF patch read from `input` to `src`, writing the patched
+/// data to `out`. See [`encode_stream`] for the memory-bound rationale.
+pub fn decode_stream<R: Read + Seek, W: Write>(
+    input: R,
+    src: R,
+    out: W,
+) -> Result<(), Error> {
+    run(input, src, out, false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// `src` spans three `SRC_BLK_SIZE` blocks and `input` spans three
+    /// `BUF_SIZE` reads, with a shared run of bytes straddling a source block
+    /// boundary, so the `XD3_GETSRCBLK`/input-refill loops each run more than
+    /// once and a copy can reference two source blocks at once.
+    #[test]
+    fn round_trip_spans_multiple_source_blocks_and_input_buffers() {
+        let block_spanning_run: Vec<u8> = (0..SRC_BLK_SIZE / 2).map(|i| (i % 251) as u8).collect();
+
+        let mut src = vec![0xAAu8; SRC_BLK_SIZE - block_spanning_run.len() / 2];
+        src.extend_from_slice(&block_spanning_run);
+        src.extend(std::iter::repeat(0xBBu8).take(SRC_BLK_SIZE));
+
+        let mut input = vec![0xCCu8; BUF_SIZE / 2];
+        input.extend_from_slice(&block_spanning_run);
+        input.extend(std::iter::repeat(0xDDu8).take(BUF_SIZE));
+
+        let mut patch = Vec::new();
+        encode_stream(Cursor::new(&input[..]), Cursor::new(&src[..]), &mut patch).unwrap();
+
+        let mut roundtripped = Vec::new();
+        decode_stream(Cursor::new(&patch[..]), Cursor::new(&src[..]), &mut roundtripped).unwrap();
+
+        assert_eq!(roundtripped, input);
+    }
+}